@@ -34,13 +34,49 @@ extern crate rtlsdr_sys as ffi;
 use std::ffi::CStr;
 use std::sync::Arc;
 
-use libc::{c_char, c_uchar, c_void};
+use libc::{c_char, c_int, c_uchar, c_void};
 
 /// Holds a list of valid gain values.
 pub type TunerGains = [i32; 32];
 
-/// Error type for this crate.
-pub type Error = ();
+/// Error type for this crate, preserving the return code from the underlying librtlsdr
+/// call that failed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The call reported that the requested value was already set (librtlsdr's `-2`
+    /// return code, notably from `rtlsdr_set_freq_correction`).
+    AlreadySet,
+    /// An argument was invalid before librtlsdr was ever called, e.g. a serial string
+    /// containing an interior NUL byte.
+    InvalidArgument,
+    /// The device reported no available tuner gain steps.
+    NoGains,
+    /// Any other librtlsdr failure, carrying its raw negative return code.
+    Io(i32),
+}
+
+impl Error {
+    /// Map a librtlsdr return code to an `Error`. Only call this for `code != 0`.
+    fn from_code(code: i32) -> Self {
+        match code {
+            -2 => Error::AlreadySet,
+            _ => Error::Io(code),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Error::AlreadySet => write!(f, "value already set"),
+            Error::InvalidArgument => write!(f, "invalid argument"),
+            Error::NoGains => write!(f, "no tuner gain steps reported"),
+            Error::Io(code) => write!(f, "librtlsdr error {}", code),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
 
 /// Result type for this crate.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -55,6 +91,59 @@ pub fn devices() -> impl Iterator<Item = &'static CStr> {
     (0..count).map(|idx| unsafe { CStr::from_ptr(ffi::rtlsdr_get_device_name(idx)) })
 }
 
+/// Identifies the tuner chip fitted to a device.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Tuner {
+    Unknown,
+    E4000,
+    FC0012,
+    FC0013,
+    FC2580,
+    R820T,
+    R828D,
+}
+
+impl From<ffi::rtlsdr_tuner> for Tuner {
+    fn from(tuner: ffi::rtlsdr_tuner) -> Self {
+        match tuner {
+            ffi::rtlsdr_tuner::RTLSDR_TUNER_E4000 => Tuner::E4000,
+            ffi::rtlsdr_tuner::RTLSDR_TUNER_FC0012 => Tuner::FC0012,
+            ffi::rtlsdr_tuner::RTLSDR_TUNER_FC0013 => Tuner::FC0013,
+            ffi::rtlsdr_tuner::RTLSDR_TUNER_FC2580 => Tuner::FC2580,
+            ffi::rtlsdr_tuner::RTLSDR_TUNER_R820T => Tuner::R820T,
+            ffi::rtlsdr_tuner::RTLSDR_TUNER_R828D => Tuner::R828D,
+            ffi::rtlsdr_tuner::RTLSDR_TUNER_UNKNOWN => Tuner::Unknown,
+        }
+    }
+}
+
+/// Selects the ADC branch used for direct sampling, bypassing the tuner to receive
+/// frequencies below its normal lower limit (e.g. HF).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DirectSampling {
+    Disabled,
+    IBranch,
+    QBranch,
+}
+
+impl DirectSampling {
+    fn from_raw(mode: c_int) -> Self {
+        match mode {
+            1 => DirectSampling::IBranch,
+            2 => DirectSampling::QBranch,
+            _ => DirectSampling::Disabled,
+        }
+    }
+
+    fn to_raw(self) -> c_int {
+        match self {
+            DirectSampling::Disabled => 0,
+            DirectSampling::IBranch => 1,
+            DirectSampling::QBranch => 2,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct UsbInfo {
     pub manufact: String,
@@ -62,6 +151,8 @@ pub struct UsbInfo {
     pub serial: String,
     pub index: u32,
     pub error: i32,
+    /// Tuner fitted to the device, or `None` if it could not be determined.
+    pub tuner: Option<Tuner>,
 }
 
 /// Create an iterator over available RTL-SDR devices.
@@ -83,6 +174,7 @@ pub fn devices_by_usbinfo() -> impl Iterator<Item = UsbInfo> {
                 manufact: String::from("Error"),
                 product: String::from("Error"),
                 serial: String::from("Error"),
+                tuner: None,
             };
         }
 
@@ -92,12 +184,19 @@ pub fn devices_by_usbinfo() -> impl Iterator<Item = UsbInfo> {
 
         print!("man {:?}, {:?}, {:?}", m, p, s);
 
+        // Briefly open the device to determine its tuner type; a device already in use
+        // elsewhere simply yields `None`.
+        let tuner = Device::open(idx)
+            .ok()
+            .map(|dev| Tuner::from(ffi::rtlsdr_get_tuner_type(*dev)));
+
         return UsbInfo {
             error: 0,
             index: idx,
             manufact: m,
             product: p,
             serial: s,
+            tuner,
         };
     })
 }
@@ -111,6 +210,22 @@ pub fn open(idx: u32) -> Result<(Controller, Reader)> {
         .map(|arc| (Controller::new(arc.clone()), Reader::new(arc)))
 }
 
+/// Try to open the RTL-SDR device with the given serial string.
+///
+/// Return a controller and reader for the device on success. This is more robust than
+/// `open()` when multiple dongles are attached, since indices can be reassigned as
+/// devices are plugged and unplugged.
+pub fn open_by_serial(serial: &str) -> Result<(Controller, Reader)> {
+    let serial = std::ffi::CString::new(serial).map_err(|_| Error::InvalidArgument)?;
+    let idx = unsafe { ffi::rtlsdr_get_index_by_serial(serial.as_ptr()) };
+
+    if idx < 0 {
+        return Err(Error::from_code(idx));
+    }
+
+    open(idx as u32)
+}
+
 /// Wraps a raw device pointer.
 struct Device(ffi::rtlsdr_dev_t);
 
@@ -119,13 +234,17 @@ impl Device {
     fn open(idx: u32) -> Result<Self> {
         let mut dev = Device(std::ptr::null_mut());
 
-        if unsafe { ffi::rtlsdr_open(&mut dev.0, idx) } == 0
-            && unsafe { ffi::rtlsdr_reset_buffer(dev.0) } == 0
-        {
-            Ok(dev)
-        } else {
-            Err(())
+        let ret = unsafe { ffi::rtlsdr_open(&mut dev.0, idx) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
         }
+
+        let ret = unsafe { ffi::rtlsdr_reset_buffer(dev.0) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
+        }
+
+        Ok(dev)
     }
 
     /// Close the device.
@@ -149,6 +268,26 @@ impl std::ops::Deref for Device {
     }
 }
 
+/// Pick the valid gain nearest `target`, keeping the first one encountered on ties.
+fn nearest_gain(gains: &[i32], target: i32) -> i32 {
+    gains
+        .iter()
+        .min_by_key(|&&gain| (target - gain).abs())
+        .copied()
+        .unwrap()
+}
+
+/// Pick the valid gain at `idx`, clamped into range.
+fn gain_by_index(gains: &[i32], idx: usize) -> i32 {
+    gains[idx.min(gains.len() - 1)]
+}
+
+/// Pick the valid gain at the given percentage (0-100) of the way through `gains`.
+fn gain_by_percent(gains: &[i32], pct: u8) -> i32 {
+    let idx = (pct as usize * gains.len() / 100).min(gains.len() - 1);
+    gains[idx]
+}
+
 /// Controls hardware parameters.
 pub struct Controller(Arc<Device>);
 
@@ -158,6 +297,11 @@ impl Controller {
         Controller(dev)
     }
 
+    /// Get the tuner chip fitted to the device.
+    pub fn tuner_type(&self) -> Tuner {
+        Tuner::from(unsafe { ffi::rtlsdr_get_tuner_type(**self.0) })
+    }
+
     /// Get the current sample rate (megasamples/sec).
     pub fn sample_rate(&self) -> u32 {
         unsafe { ffi::rtlsdr_get_sample_rate(**self.0) }
@@ -165,10 +309,12 @@ impl Controller {
 
     /// Set the sample rate (megasamples/sec).
     pub fn set_sample_rate(&mut self, rate: u32) -> Result<()> {
-        if unsafe { ffi::rtlsdr_set_sample_rate(**self.0, rate) } == 0 {
+        let ret = unsafe { ffi::rtlsdr_set_sample_rate(**self.0, rate) };
+
+        if ret == 0 {
             Ok(())
         } else {
-            Err(())
+            Err(Error::from_code(ret))
         }
     }
 
@@ -179,10 +325,12 @@ impl Controller {
 
     /// Set the center frequency (Hz).
     pub fn set_center_freq(&mut self, freq: u32) -> Result<()> {
-        if unsafe { ffi::rtlsdr_set_center_freq(**self.0, freq) } == 0 {
+        let ret = unsafe { ffi::rtlsdr_set_center_freq(**self.0, freq) };
+
+        if ret == 0 {
             Ok(())
         } else {
-            Err(())
+            Err(Error::from_code(ret))
         }
     }
 
@@ -190,10 +338,12 @@ impl Controller {
     ///
     /// Note that this is not bit DEPTH which is fixed at 8 in hardware.
     pub fn set_bandwidth(&mut self, bw: u32) -> Result<()> {
-        if unsafe { ffi::rtlsdr_set_tuner_bandwidth(**self.0, bw) } == 0 {
+        let ret = unsafe { ffi::rtlsdr_set_tuner_bandwidth(**self.0, bw) };
+
+        if ret == 0 {
             Ok(())
         } else {
-            Err(())
+            Err(Error::from_code(ret))
         }
     }
 
@@ -210,7 +360,7 @@ impl Controller {
         if ret == 0 || ret == -2 {
             Ok(())
         } else {
-            Err(())
+            Err(Error::from_code(ret))
         }
     }
 
@@ -218,38 +368,54 @@ impl Controller {
     ///
     /// Note that this also disables manual tuner gain.
     pub fn enable_agc(&mut self) -> Result<()> {
-        if unsafe { ffi::rtlsdr_set_tuner_gain_mode(**self.0, 0) } == 0
-            && unsafe { ffi::rtlsdr_set_agc_mode(**self.0, 1) } == 0
-        {
-            Ok(())
-        } else {
-            Err(())
+        let ret = unsafe { ffi::rtlsdr_set_tuner_gain_mode(**self.0, 0) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
         }
+
+        let ret = unsafe { ffi::rtlsdr_set_agc_mode(**self.0, 1) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
+        }
+
+        Ok(())
     }
 
     /// Disable the hardware AGC.
     ///
     /// Note that this also enables manual tuner gain.
     pub fn disable_agc(&mut self) -> Result<()> {
-        if unsafe { ffi::rtlsdr_set_tuner_gain_mode(**self.0, 1) } == 0
-            && unsafe { ffi::rtlsdr_set_agc_mode(**self.0, 0) } == 0
-        {
-            Ok(())
-        } else {
-            Err(())
+        let ret = unsafe { ffi::rtlsdr_set_tuner_gain_mode(**self.0, 1) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
+        }
+
+        let ret = unsafe { ffi::rtlsdr_set_agc_mode(**self.0, 0) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
         }
+
+        Ok(())
     }
 
     /// Get the list of valid tuner gain values.
     ///
     /// Each value represents a dB gain with the decimal place shifted right. For example,
     /// the value 496 represents 49.6dB.
-    pub fn tuner_gains<'a>(&self, gains: &'a mut TunerGains) -> &'a [i32] {
+    pub fn tuner_gains<'a>(&self, gains: &'a mut TunerGains) -> Result<&'a [i32]> {
         let ret = unsafe { ffi::rtlsdr_get_tuner_gains(**self.0, gains.as_mut_ptr()) };
 
-        assert!(ret > 0 && ret as usize <= gains.len());
+        if ret < 0 {
+            return Err(Error::from_code(ret));
+        }
+
+        if ret == 0 {
+            return Err(Error::NoGains);
+        }
+
+        assert!(ret as usize <= gains.len());
 
-        &gains[..ret as usize]
+        Ok(&gains[..ret as usize])
     }
 
     /// Get the current tuner gain in the same format as that returned by `tuner_gains()`.
@@ -261,15 +427,77 @@ impl Controller {
     ///
     /// Note that this also disables the hardware AGC.
     pub fn set_tuner_gain(&mut self, gain: i32) -> Result<()> {
-        if unsafe { ffi::rtlsdr_set_tuner_gain_mode(**self.0, 1) } == 0
-            && unsafe { ffi::rtlsdr_set_tuner_gain(**self.0, gain) } == 0
-        {
+        let ret = unsafe { ffi::rtlsdr_set_tuner_gain_mode(**self.0, 1) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
+        }
+
+        let ret = unsafe { ffi::rtlsdr_set_tuner_gain(**self.0, gain) };
+        if ret != 0 {
+            return Err(Error::from_code(ret));
+        }
+
+        Ok(())
+    }
+
+    /// Set the gain of a single IF amplifier stage, for finer control of the gain
+    /// distribution across the signal chain than the one-knob `set_tuner_gain()`.
+    ///
+    /// Requires manual gain mode (see `set_tuner_gain()`). Stage support is
+    /// tuner-dependent; this is most useful on E4000-class tuners (check
+    /// `tuner_type()`), which have multiple independently adjustable IF stages.
+    pub fn set_if_gain(&mut self, stage: i32, gain: i32) -> Result<()> {
+        let ret = unsafe { ffi::rtlsdr_set_tuner_if_gain(**self.0, stage, gain) };
+
+        if ret == 0 {
             Ok(())
         } else {
-            Err(())
+            Err(Error::from_code(ret))
         }
     }
 
+    /// Set the tuner gain to the valid value nearest to the given target, in the same
+    /// format as that returned by `tuner_gains()`.
+    ///
+    /// Note that this also disables the hardware AGC. If multiple valid values are equally
+    /// near the target, the first one encountered is used.
+    pub fn set_nearest_gain(&mut self, target: i32) -> Result<()> {
+        let mut gains = [0; 32];
+        let gains = self.tuner_gains(&mut gains)?;
+
+        let gain = nearest_gain(gains, target);
+
+        self.set_tuner_gain(gain)
+    }
+
+    /// Set the tuner gain to the valid value at the given index into `tuner_gains()`,
+    /// clamped to the valid range.
+    ///
+    /// Note that this also disables the hardware AGC.
+    pub fn set_gain_by_index(&mut self, idx: usize) -> Result<()> {
+        let mut gains = [0; 32];
+        let gains = self.tuner_gains(&mut gains)?;
+
+        let gain = gain_by_index(gains, idx);
+
+        self.set_tuner_gain(gain)
+    }
+
+    /// Set the tuner gain to the valid value at the given percentage, from 0 (lowest
+    /// gain) to 100 (highest gain), of the way through `tuner_gains()`.
+    ///
+    /// The index used is `pct * count / 100`, rounded down and clamped to the last entry.
+    ///
+    /// Note that this also disables the hardware AGC.
+    pub fn set_gain_by_percent(&mut self, pct: u8) -> Result<()> {
+        let mut gains = [0; 32];
+        let gains = self.tuner_gains(&mut gains)?;
+
+        let gain = gain_by_percent(gains, pct);
+
+        self.set_tuner_gain(gain)
+    }
+
     /// Cancel an asynchronous read if one is running.
     pub fn cancel_async_read(&mut self) {
         unsafe {
@@ -282,16 +510,144 @@ impl Controller {
     /// This will clear any samples that have been received by the device but not yet read
     /// by an async_read.
     pub fn reset_buffer(&mut self) -> Result<()> {
-        if unsafe { ffi::rtlsdr_reset_buffer(**self.0) } == 0 {
+        let ret = unsafe { ffi::rtlsdr_reset_buffer(**self.0) };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Get the current direct sampling mode.
+    pub fn direct_sampling(&self) -> DirectSampling {
+        DirectSampling::from_raw(unsafe { ffi::rtlsdr_get_direct_sampling(**self.0) })
+    }
+
+    /// Set the direct sampling mode, for receiving below the tuner's normal lower limit
+    /// (e.g. HF) by feeding the RTL2832 ADC directly from the I or Q branch.
+    pub fn set_direct_sampling(&mut self, mode: DirectSampling) -> Result<()> {
+        let ret = unsafe { ffi::rtlsdr_set_direct_sampling(**self.0, mode.to_raw()) };
+
+        if ret == 0 {
             Ok(())
         } else {
-            Err(())
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Get whether offset tuning is enabled.
+    pub fn offset_tuning(&self) -> bool {
+        unsafe { ffi::rtlsdr_get_offset_tuning(**self.0) } == 1
+    }
+
+    /// Enable or disable offset tuning, which pushes the DC spike out of band for tuners
+    /// that don't support zero-IF or direct sampling.
+    pub fn set_offset_tuning(&mut self, on: bool) -> Result<()> {
+        let ret = unsafe { ffi::rtlsdr_set_offset_tuning(**self.0, on as c_int) };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Read bytes from the device's EEPROM into `buf`, starting at `offset`.
+    ///
+    /// Returns the number of bytes actually read, which is `buf.len()` capped to the
+    /// 16-bit length the underlying API accepts.
+    pub fn read_eeprom(&self, offset: u8, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len().min(u16::max_value() as usize);
+
+        let ret =
+            unsafe { ffi::rtlsdr_read_eeprom(**self.0, buf.as_mut_ptr(), offset, len as u16) };
+
+        if ret == 0 {
+            Ok(len)
+        } else {
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Write `data` into the device's EEPROM, starting at `offset`.
+    ///
+    /// This can be used to set the identity strings (manufacturer, product, serial)
+    /// reported by `devices_by_usbinfo()` and used by `open_by_serial()`.
+    pub fn write_eeprom(&mut self, offset: u8, data: &[u8]) -> Result<()> {
+        let len = data.len().min(u16::max_value() as usize);
+
+        let ret =
+            unsafe { ffi::rtlsdr_write_eeprom(**self.0, data.as_ptr(), offset, len as u16) };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Enable or disable the built-in test-pattern mode.
+    ///
+    /// In test mode the RTL2832 emits a deterministic 8-bit incrementing counter on the
+    /// I/Q stream instead of tuner data. Check received chunks against this pattern with
+    /// `Reader::verify_test_pattern()` to validate buffering and threading before
+    /// trusting real captures.
+    pub fn set_test_mode(&mut self, on: bool) -> Result<()> {
+        let ret = unsafe { ffi::rtlsdr_set_testmode(**self.0, on as c_int) };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Get the current crystal frequencies (Hz) for the RTL2832 reference clock and the
+    /// tuner PLL.
+    pub fn xtal_freq(&self) -> Result<(u32, u32)> {
+        let mut rtl_freq = 0;
+        let mut tuner_freq = 0;
+
+        let ret =
+            unsafe { ffi::rtlsdr_get_xtal_freq(**self.0, &mut rtl_freq, &mut tuner_freq) };
+
+        if ret == 0 {
+            Ok((rtl_freq, tuner_freq))
+        } else {
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Set the crystal frequencies (Hz) for the RTL2832 reference clock and the tuner
+    /// PLL.
+    ///
+    /// Cheap dongles have crystal error that ppm correction alone doesn't fully model;
+    /// this lets users who have characterized their hardware against a known reference
+    /// set exact clock values instead.
+    pub fn set_xtal_freq(&mut self, rtl_freq: u32, tuner_freq: u32) -> Result<()> {
+        let ret = unsafe { ffi::rtlsdr_set_xtal_freq(**self.0, rtl_freq, tuner_freq) };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(Error::from_code(ret))
         }
     }
 }
 
 unsafe impl Send for Controller {}
 
+/// Reports how well a chunk of samples matches the test-pattern counter emitted by a
+/// device in test mode (see `Controller::set_test_mode()`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TestPatternReport {
+    /// Number of bytes that broke the expected incrementing sequence.
+    pub discontinuities: usize,
+    /// Index of the first discontinuity, if any.
+    pub first_discontinuity: Option<usize>,
+}
+
 /// Reads I/Q samples.
 pub struct Reader(Arc<Device>);
 
@@ -301,6 +657,31 @@ impl Reader {
         Reader(dev)
     }
 
+    /// Read I/Q samples into the given buffer, blocking until it is filled or the read
+    /// otherwise falls short.
+    ///
+    /// Returns the number of bytes actually read, which may be less than `buf.len()` on a
+    /// short read. Call `Controller::reset_buffer()` before the first read to clear out any
+    /// samples the device buffered while it was idle.
+    pub fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut read = 0;
+
+        let ret = unsafe {
+            ffi::rtlsdr_read_sync(
+                **self.0,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as c_int,
+                &mut read,
+            )
+        };
+
+        if ret == 0 {
+            Ok(read as usize)
+        } else {
+            Err(Error::from_code(ret))
+        }
+    }
+
     /// Begin reading I/Q samples, buffering into the given number of chunks, with each
     /// chunk holding the given number of bytes. The given callback is called whenever new
     /// samples are available, receiving a chunk at a time.
@@ -318,7 +699,30 @@ impl Reader {
         if ret == 0 {
             Ok(())
         } else {
-            Err(())
+            Err(Error::from_code(ret))
+        }
+    }
+
+    /// Verify that a chunk received while the device is in test mode forms a continuous,
+    /// wrapping 8-bit incrementing ramp, as emitted by `Controller::set_test_mode()`.
+    ///
+    /// The first byte of `buf` is taken on faith as the start of the ramp; every
+    /// following byte is checked against the byte before it plus one (mod 256), so the
+    /// check picks up again from the new value after each discontinuity.
+    pub fn verify_test_pattern(buf: &[u8]) -> TestPatternReport {
+        let mut discontinuities = 0;
+        let mut first_discontinuity = None;
+
+        for (idx, window) in buf.windows(2).enumerate() {
+            if window[1] != window[0].wrapping_add(1) {
+                discontinuities += 1;
+                first_discontinuity.get_or_insert(idx + 1);
+            }
+        }
+
+        TestPatternReport {
+            discontinuities,
+            first_discontinuity,
         }
     }
 }
@@ -340,6 +744,84 @@ unsafe impl Send for Reader {}
 mod tests {
     use crate::devices;
     use crate::devices_by_usbinfo;
+    use crate::{gain_by_index, gain_by_percent, nearest_gain, Reader, TestPatternReport};
+
+    #[test]
+    fn test_verify_test_pattern_clean_ramp() {
+        let buf: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(
+            Reader::verify_test_pattern(&buf),
+            TestPatternReport {
+                discontinuities: 0,
+                first_discontinuity: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_test_pattern_wraps_at_255() {
+        let buf = [253, 254, 255, 0, 1, 2];
+
+        assert_eq!(
+            Reader::verify_test_pattern(&buf),
+            TestPatternReport {
+                discontinuities: 0,
+                first_discontinuity: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_test_pattern_single_break() {
+        let buf = [0, 1, 2, 10, 11, 12];
+
+        let report = Reader::verify_test_pattern(&buf);
+
+        assert_eq!(report.discontinuities, 1);
+        assert_eq!(report.first_discontinuity, Some(3));
+    }
+
+    #[test]
+    fn test_verify_test_pattern_multiple_breaks() {
+        let buf = [0, 1, 10, 11, 20, 21];
+
+        let report = Reader::verify_test_pattern(&buf);
+
+        assert_eq!(report.discontinuities, 2);
+        assert_eq!(report.first_discontinuity, Some(2));
+    }
+
+    #[test]
+    fn test_nearest_gain_ties_keep_first() {
+        // 10 and 20 are equally near 15; the first encountered, 10, should win.
+        let gains = [0, 10, 20, 30];
+
+        assert_eq!(nearest_gain(&gains, 15), 10);
+    }
+
+    #[test]
+    fn test_nearest_gain_exact_match() {
+        let gains = [0, 10, 20, 30];
+
+        assert_eq!(nearest_gain(&gains, 20), 20);
+    }
+
+    #[test]
+    fn test_gain_by_index_clamps() {
+        let gains = [0, 10, 20, 30];
+
+        assert_eq!(gain_by_index(&gains, 1), 10);
+        assert_eq!(gain_by_index(&gains, 99), 30);
+    }
+
+    #[test]
+    fn test_gain_by_percent_bounds() {
+        let gains = [0, 10, 20, 30];
+
+        assert_eq!(gain_by_percent(&gains, 0), 0);
+        assert_eq!(gain_by_percent(&gains, 100), 30);
+    }
 
     #[test]
     fn test_device_by_usbinfo_count() {